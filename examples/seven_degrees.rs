@@ -1,11 +1,9 @@
-use bincode::{deserialize, serialize};
 use bitvec::prelude::*;
 use clap::Parser;
-use futures::future::try_join_all;
 use liquid_ml::{
+    application::Application,
     dataframe::{Data, Row, Rower},
     error::LiquidError,
-    LiquidML,
 };
 use log::Level;
 use serde::{Deserialize, Serialize};
@@ -149,10 +147,14 @@ impl Rower for UserRower {
 async fn main() -> Result<(), LiquidError> {
     let opts: Opts = Opts::parse();
     simple_logger::init_with_level(Level::Info).unwrap();
-    let mut app =
-        LiquidML::new(&opts.my_address, &opts.server_address, opts.num_nodes)
-            .await?;
-    app.df_from_sor("commits", &opts.commits).await?;
+    let mut app = Application::from_sor(
+        &opts.commits,
+        &opts.my_address,
+        &opts.server_address,
+        opts.num_nodes,
+        None,
+    )
+    .await?;
 
     // assume the max of pid is <= num_lines
     let num_projects = 126_000_000;
@@ -162,57 +164,46 @@ async fn main() -> Result<(), LiquidError> {
     let mut projects = BitVec::repeat(false, num_projects);
     for i in 0..opts.degrees {
         println!("degree {}", i);
-        let mut pr = ProjectRower::new(num_projects, users, projects);
-        // Node 1 will get the rower back and send it to all the other nodes
-        // other nodes will wait for node 1 to send the final combined rower to
-        // them
-        pr = match app.map("commits", pr).await? {
-            None => {
-                let blob =
-                    { app.blob_receiver.lock().await.recv().await.unwrap() };
-                deserialize(&blob[..])?
-            }
-            Some(rower) => {
-                let serialized = serialize(&rower)?;
-                let mut futs = Vec::new();
-                for i in 2..(app.num_nodes + 1) {
-                    futs.push(app.kv.send_blob(i, serialized.clone()));
-                }
-                try_join_all(futs).await?;
-
-                rower
-            }
-        };
-        dbg!("finished projects rower");
+        let pr = ProjectRower::new(num_projects, users, projects);
+        // Every node gets the combined rower back directly, via
+        // map_allreduce's recursive-doubling all-reduce -- no manual
+        // node-1 broadcast.
+        let pr = app.map_allreduce("commits", pr).await?;
         users = pr.users;
         projects = pr.new_projects;
-        let mut ur = UserRower::new(num_users, users, projects);
-        // Node 1 will get the rower back and send it to all the other nodes
-        // other nodes will wait for node 1 to send the final combined rower to
-        // them
-        ur = match app.map("commits", ur).await? {
-            None => {
-                let blob =
-                    { app.blob_receiver.lock().await.recv().await.unwrap() };
-                deserialize(&blob[..])?
-            }
-            Some(rower) => {
-                let serialized = serialize(&rower)?;
-                let mut futs = Vec::new();
-                for i in 2..(app.num_nodes + 1) {
-                    futs.push(app.kv.send_blob(i, serialized.clone()));
-                }
-                try_join_all(futs).await?;
-
-                rower
-            }
-        };
-        dbg!("finished users rower");
+        let ur = UserRower::new(num_users, users, projects);
+        let ur = app.map_allreduce("commits", ur).await?;
         users = ur.new_users;
         projects = ur.projects;
+
+        // metrics_snapshot's own reporting round doesn't record into
+        // `self.metrics` (see `record` on `send_round`/`recv_round`), so
+        // this call sees only the map/join/exchange traffic of the
+        // `map_allreduce`s above. The histograms are never reset, so that
+        // covers every degree so far, not just this one.
+        let metrics = app.metrics_snapshot().await?;
+        println!(
+            "degree {} local map p50/p90/p99 (ns): {}/{}/{}",
+            i,
+            metrics.local_map.p50,
+            metrics.local_map.p90,
+            metrics.local_map.p99
+        );
+        println!(
+            "degree {} join p50/p90/p99 (ns): {}/{}/{}",
+            i, metrics.join.p50, metrics.join.p90, metrics.join.p99
+        );
+        println!(
+            "degree {} exchange p50/p90/p99 (ns): {}/{}/{}",
+            i,
+            metrics.exchange.p50,
+            metrics.exchange.p90,
+            metrics.exchange.p99
+        );
     }
     println!("num users found: {}", users.count_ones());
-    app.kill_notifier.notified().await;
+
+    app.go().await;
 
     Ok(())
 }
@@ -4,29 +4,702 @@ use crate::error::LiquidError;
 use crate::kv::{KVMessage, KVStore, Key, Value};
 use crate::network::client::Client;
 use bincode::{deserialize, serialize};
+use bitvec::prelude::*;
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key as AeadKey, Nonce};
+use futures::future::try_join_all;
+use futures::stream::{self, Stream, StreamExt};
+use rand::Rng;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
 use tokio::sync::{Notify, RwLock};
 use tokio::task::JoinHandle;
+use tokio::time::sleep;
 
-pub struct Application {
+/// A serialized `Rower` plus the round it was sent for, so a node can match
+/// it up even if blobs from different rounds arrive out of order.
+#[derive(Serialize, Deserialize)]
+struct RoundBlob {
+    round: u32,
+    payload: Vec<u8>,
+}
+
+/// Tags every blob sent over `blob_receiver` so a node can tell a
+/// reduction-round payload ([`RoundBlob`]) apart from a [`Request`] pushed
+/// by [`Application::notify_put`], since both ride the same channel.
+#[derive(Serialize, Deserialize)]
+enum Envelope {
+    Round(RoundBlob),
+    Request(Request),
+}
+
+/// Authenticated encryption for inter-node blob transport. The key is
+/// derived from a shared secret given to [`Application::new`]; blobs are
+/// sealed as a random 12-byte nonce followed by the ChaCha20-Poly1305
+/// ciphertext (with its AEAD tag appended).
+struct BlobCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl BlobCipher {
+    fn new(shared_secret: &str) -> Self {
+        let key = Sha256::digest(shared_secret.as_bytes());
+        BlobCipher {
+            cipher: ChaCha20Poly1305::new(AeadKey::from_slice(&key)),
+        }
+    }
+
+    fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce_bytes = [0u8; 12];
+        rand::thread_rng().fill(&mut nonce_bytes);
+        let mut sealed = nonce_bytes.to_vec();
+        sealed.extend(
+            self.cipher
+                .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+                .expect("chacha20poly1305 encryption failure"),
+        );
+        sealed
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, LiquidError> {
+        if sealed.len() < 12 {
+            return Err(LiquidError::DecryptionFailed);
+        }
+        let (nonce_bytes, ciphertext) = sealed.split_at(12);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|_| LiquidError::DecryptionFailed)
+    }
+}
+
+/// Per-`df_name` have/want/requested bitfields for a Dat-style feed
+/// exchange over dataframe chunks, indexed by 0-based partition.
+struct ChunkSync {
+    have: BitVec,
+    want: BitVec,
+    requested: BitVec,
+}
+
+impl ChunkSync {
+    /// `own_chunk` starts out `have`, since it's already resident via
+    /// `from_sor`.
+    fn new(n_chunks: usize, own_chunk: usize) -> Self {
+        let mut have = BitVec::repeat(false, n_chunks);
+        have.set(own_chunk, true);
+        ChunkSync {
+            have,
+            want: BitVec::repeat(false, n_chunks),
+            requested: BitVec::repeat(false, n_chunks),
+        }
+    }
+
+    /// Folds in a fresh set of wanted chunks and returns the ones still
+    /// needing a fetch (wanted, not held, not already in flight), marking
+    /// them requested.
+    fn chunks_to_fetch(&mut self, wanted: &BitVec) -> Vec<usize> {
+        self.want |= wanted.clone();
+        let mut pending = self.want.clone();
+        pending &= !self.have.clone();
+        pending &= !self.requested.clone();
+        self.requested |= pending.clone();
+        pending.iter_ones().collect()
+    }
+
+    /// Marks a chunk resolved once its blob has arrived.
+    fn on_chunk_arrived(&mut self, chunk: usize) {
+        self.have.set(chunk, true);
+        self.want.set(chunk, false);
+        self.requested.set(chunk, false);
+    }
+}
+
+/// What [`Application::sync_chunks`] exchanges with a peer before fetching
+/// anything: this node's `have` bitfield for `df_name`, so each side can
+/// tell which of the chunks it wants the other side actually holds.
+#[derive(Serialize, Deserialize)]
+struct ChunkSyncMessage {
+    df_name: String,
+    have: BitVec,
+}
+
+/// Offset added to round numbers in [`Application::metrics_snapshot`] so
+/// they don't collide with a concurrently running `pmap`/`map_allreduce`
+/// reduction, which both number their rounds from 0 over the same
+/// `blob_receiver`.
+const METRICS_ROUND_BASE: u32 = 1 << 20;
+
+/// Base offset added to round numbers in [`Application::sync_chunks`]'s
+/// have/want exchange, for the same reason as [`METRICS_ROUND_BASE`]. Each
+/// peer pair (and `df_name` synced with that pair) also gets its own offset
+/// on top of this base -- see `chunk_sync_round`.
+const CHUNK_SYNC_ROUND_BASE: u32 = 1 << 21;
+
+/// How many passes over every peer [`Application::sync_chunks`] makes
+/// before giving up and returning `LiquidError::TimedOut`, e.g. if a peer
+/// never ends up loading the partition this node is after.
+const SYNC_CHUNKS_MAX_PASSES: u32 = 30;
+
+/// How long [`Application::sync_chunks`] sleeps between passes over every
+/// peer once one leaves chunks still missing, so a normal startup race
+/// (a peer hasn't loaded/published its partition yet) doesn't busy-spin
+/// full network round-trips against every peer.
+const SYNC_CHUNKS_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A round number for the have/want exchange between 0-based node indices
+/// `a` and `b` over `df_name`, symmetric in `a`/`b` (so both sides compute
+/// the same value regardless of which peer each iterates to first). Folds a
+/// hash of `df_name` into the low 16 bits so two dataframes synced between
+/// the same node pair don't land on the same round and decode each other's
+/// `ChunkSyncMessage`/chunk data.
+fn chunk_sync_round(a: usize, b: usize, num_nodes: usize, df_name: &str) -> u32 {
+    let (lo, hi) = (a.min(b), a.max(b));
+    let pair = (lo * num_nodes + hi) as u32;
+    let mut hasher = DefaultHasher::new();
+    df_name.hash(&mut hasher);
+    let df_tag = (hasher.finish() as u32) & 0xffff;
+    CHUNK_SYNC_ROUND_BASE
+        .wrapping_add(pair.wrapping_mul(1 << 16))
+        .wrapping_add(df_tag)
+}
+
+/// A lock-free histogram over 64 exponentially spaced buckets: bucket `i`
+/// counts observations in `[2^(i-1), 2^i)` (bucket 0 catches `0`). Good
+/// enough resolution for latency-in-nanoseconds or size-in-bytes
+/// distributions without the contention of a mutex-guarded structure on
+/// the recording fast path.
+struct Histogram {
+    buckets: [AtomicU64; 64],
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn record(&self, value: u64) {
+        let bucket = (64 - value.leading_zeros() as usize).min(63);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot(
+            self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect(),
+        )
+    }
+}
+
+/// A wire-serializable copy of a [`Histogram`]'s bucket counts, exchanged
+/// between nodes by [`Application::metrics_snapshot`] since `AtomicU64`
+/// itself isn't `Serialize`.
+#[derive(Serialize, Deserialize, Clone)]
+struct HistogramSnapshot(Vec<u64>);
+
+impl HistogramSnapshot {
+    fn merge(&mut self, other: &HistogramSnapshot) {
+        for (mine, theirs) in self.0.iter_mut().zip(other.0.iter()) {
+            *mine += theirs;
+        }
+    }
+
+    /// Estimates the value at percentile `p` (`0.0..=1.0`) as the upper
+    /// edge of the bucket whose cumulative count first reaches `p` of the
+    /// total observations.
+    fn percentile(&self, p: f64) -> u64 {
+        let total: u64 = self.0.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+        let target = (p * total as f64).ceil() as u64;
+        let mut cumulative = 0u64;
+        for (i, &count) in self.0.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= target {
+                return 1u64 << i;
+            }
+        }
+        1u64 << (self.0.len() - 1)
+    }
+
+    fn report(&self) -> StageReport {
+        StageReport {
+            p50: self.percentile(0.50),
+            p90: self.percentile(0.90),
+            p99: self.percentile(0.99),
+        }
+    }
+}
+
+/// The p50/p90/p99 of one [`MetricsReport`] stage, in whatever unit that
+/// stage's histogram records (nanoseconds for timings, bytes for
+/// `blob_bytes`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StageReport {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+/// A cluster-wide snapshot returned by [`Application::metrics_snapshot`]:
+/// `local_map` is time spent in this node's own `DataFrame::pmap` call
+/// (visiting every row *and* folding the per-thread rowers together
+/// in-node -- the two don't split out further), `join` is time spent in
+/// the cross-node `Rower::join` once a round's blob has arrived,
+/// `exchange` is time spent waiting on that blob to cross the network,
+/// and `blob_bytes` is the size of those blobs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricsReport {
+    pub local_map: StageReport,
+    pub join: StageReport,
+    pub exchange: StageReport,
+    pub blob_bytes: StageReport,
+}
+
+/// The live, per-node histograms backing [`MetricsReport`].
+struct Metrics {
+    local_map: Histogram,
+    join: Histogram,
+    exchange: Histogram,
+    blob_bytes: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        Metrics {
+            local_map: Histogram::new(),
+            join: Histogram::new(),
+            exchange: Histogram::new(),
+            blob_bytes: Histogram::new(),
+        }
+    }
+}
+
+/// A wire-serializable bundle of one node's [`Metrics`], merged pairwise
+/// (bucket counts just add) by the all-reduce in
+/// [`Application::metrics_snapshot`].
+#[derive(Serialize, Deserialize, Clone)]
+struct MetricsBundle {
+    local_map: HistogramSnapshot,
+    join: HistogramSnapshot,
+    exchange: HistogramSnapshot,
+    blob_bytes: HistogramSnapshot,
+}
+
+impl MetricsBundle {
+    fn merge(&mut self, other: &MetricsBundle) {
+        self.local_map.merge(&other.local_map);
+        self.join.merge(&other.join);
+        self.exchange.merge(&other.exchange);
+        self.blob_bytes.merge(&other.blob_bytes);
+    }
+
+    fn into_report(self) -> MetricsReport {
+        MetricsReport {
+            local_map: self.local_map.report(),
+            join: self.join.report(),
+            exchange: self.exchange.report(),
+            blob_bytes: self.blob_bytes.report(),
+        }
+    }
+}
+
+/// A monotonically increasing generation id, e.g. one per `degrees`
+/// iteration of a loop like seven-degrees's, used to key [`EpochCache`].
+pub type Epoch = u64;
+
+/// Epoch-keyed memoization over deserialized `Rower`s and dataframe
+/// segments. Values are type-erased; [`Application::cache_get_or_init`]
+/// downcasts back to the caller's concrete type.
+struct EpochCache {
+    generations: RwLock<HashMap<Epoch, HashMap<String, Arc<dyn Any + Send + Sync>>>>,
+    // Generations more than this many epochs behind the current one are
+    // dropped on every insert.
+    window: Epoch,
+}
+
+impl EpochCache {
+    fn new(window: Epoch) -> Self {
+        EpochCache {
+            generations: RwLock::new(HashMap::new()),
+            window,
+        }
+    }
+
+    async fn get_or_init<T, F, Fut>(
+        &self,
+        epoch: Epoch,
+        key: &str,
+        init: F,
+    ) -> Arc<T>
+    where
+        T: Any + Send + Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        if let Some(cached) = self.lookup::<T>(epoch, key).await {
+            return cached;
+        }
+
+        // Construct outside any lock: `init` may be arbitrary async work
+        // (I/O, another `cache_get_or_init` call), and holding a lock
+        // across it would serialize unrelated epoch/key misses behind
+        // this one -- or deadlock outright, since `RwLock` isn't
+        // reentrant. Two callers can race and both construct a value for
+        // the same miss; the write lock below resolves that by keeping
+        // whichever was inserted first.
+        let value: Arc<dyn Any + Send + Sync> = Arc::new(init().await);
+
+        let mut generations = self.generations.write().await;
+        if let Some(cached) = generations
+            .get(&epoch)
+            .and_then(|gen| gen.get(key))
+            .and_then(|v| v.clone().downcast::<T>().ok())
+        {
+            return cached;
+        }
+        generations
+            .entry(epoch)
+            .or_insert_with(HashMap::new)
+            .insert(key.to_string(), value.clone());
+        generations.retain(|&gen_epoch, _| {
+            epoch.saturating_sub(gen_epoch) <= self.window
+        });
+        value.downcast::<T>().expect("just inserted as T")
+    }
+
+    async fn lookup<T: Any + Send + Sync>(
+        &self,
+        epoch: Epoch,
+        key: &str,
+    ) -> Option<Arc<T>> {
+        let generations = self.generations.read().await;
+        generations
+            .get(&epoch)?
+            .get(key)?
+            .clone()
+            .downcast::<T>()
+            .ok()
+    }
+}
+
+/// This node's role in round `k` of `pmap`'s logarithmic-depth tree
+/// reduction over `num_nodes` total nodes: wait on a partner's blob, send
+/// its own to a partner and be done, or carry its value into the next
+/// round unchanged (no partner this round).
+#[derive(Debug, PartialEq, Eq)]
+enum TreeRole {
+    Recv { partner: usize },
+    Send { partner: usize },
+    Carry,
+}
+
+/// A node whose `idx` is divisible by `2^(k+1)` waits on the node at
+/// `idx + 2^k` (if it exists); one divisible by `2^k` but not `2^(k+1)`
+/// sends to `idx - 2^k` and is done; everyone else carries `idx` unchanged
+/// into round `k + 1`.
+fn tree_reduce_role(idx: usize, k: u32, num_nodes: usize) -> TreeRole {
+    let step = 1usize << k;
+    if idx % (step * 2) == 0 {
+        if idx + step < num_nodes {
+            TreeRole::Recv { partner: idx + step }
+        } else {
+            TreeRole::Carry
+        }
+    } else if idx % step == 0 {
+        TreeRole::Send { partner: idx - step }
+    } else {
+        TreeRole::Carry
+    }
+}
+
+/// The fold-in/fold-out parameters of `map_allreduce`'s non-power-of-2
+/// handling for a cluster of `num_nodes` total nodes: the largest power
+/// of two `pow2 <= num_nodes`, the `extra` high-numbered nodes folded
+/// into that subset, and how many rounds of recursive doubling run
+/// within it.
+fn allreduce_plan(num_nodes: usize) -> (usize, usize, u32) {
+    let mut pow2 = 1usize;
+    while pow2 * 2 <= num_nodes {
+        pow2 *= 2;
+    }
+    let extra = num_nodes - pow2;
+    let doubling_rounds = pow2.trailing_zeros();
+    (pow2, extra, doubling_rounds)
+}
+
+/// A pluggable wire format for moving serialized values (chiefly `Rower`s)
+/// between nodes. `Application` defaults to [`Bincode`], but accepts any
+/// `Codec` so a cluster can opt into a self-describing format like
+/// [`Cbor`] when rolling out new `Rower` fields across a heterogeneous set
+/// of nodes without breaking the wire protocol.
+///
+/// This only covers the round-tagged blob payloads in this file (`pmap`,
+/// `map_allreduce`, `metrics_snapshot`, `sync_chunks`); `KVStore` (not part of this source tree) has its own wire format for
+/// `DataFrame` partitions and isn't made generic here.
+pub trait Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, LiquidError>;
+
+    fn decode<T: DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<T, LiquidError>;
+}
+
+/// The default wire format: compact, but every node must agree on the
+/// exact `Rower` layout since `bincode` carries no field names or tags.
+#[derive(Default, Clone, Copy)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, LiquidError> {
+        Ok(serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<T, LiquidError> {
+        Ok(deserialize(bytes)?)
+    }
+}
+
+/// A self-describing wire format, tolerant of `Rower`s gaining or losing
+/// fields across a rolling upgrade, at the cost of larger messages than
+/// [`Bincode`].
+#[derive(Default, Clone, Copy)]
+pub struct Cbor;
+
+impl Codec for Cbor {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, LiquidError> {
+        serde_cbor::to_vec(value).map_err(|_| LiquidError::EncodingFailed)
+    }
+
+    fn decode<T: DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<T, LiquidError> {
+        serde_cbor::from_slice(bytes).map_err(|_| LiquidError::EncodingFailed)
+    }
+}
+
+/// A request pushed to another node over the blob channel. `PutBlob` is the
+/// only variant, rather than one per key/value operation: it's how
+/// [`Application::notify_put`] tells every other node about a write, so
+/// [`dispatch_blobs`] can route it into [`publish`](Application::publish) on
+/// arrival. `kv.get` and [`Application::subscribe`] are purely local and
+/// have no wire counterpart, so they aren't modeled here.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Request {
+    PutBlob { key: Key, data: Vec<u8> },
+}
+
+pub struct Application<C: Codec = Bincode> {
     pub kv: Arc<KVStore>,
     pub node_id: usize,
-    pub blob_receiver: Receiver<Value>,
+    // Round-tagged payloads, forwarded here by `dispatch_blobs` (spawned in
+    // `new_with_codec`); drained by `recv_round_blob`. An `Err` means
+    // `dispatch_blobs` couldn't open or decode an inbound blob -- since the
+    // round it was meant for is unrecoverable at that point, it's surfaced
+    // to whichever round-based call is waiting next instead of silently
+    // dropped.
+    round_blob_receiver: Receiver<Result<(u32, Vec<u8>), LiquidError>>,
     // TODO: maybe use a runtime here
     msg_processor: JoinHandle<()>,
     conn_processor: JoinHandle<()>,
+    // Drains the raw wire channel independent of any round-based call, so
+    // `subscribe` gets `Request::PutBlob` pushes even when nothing is
+    // currently mid-`pmap`/`map_allreduce`/`metrics_snapshot`/`sync_chunks`.
+    // See `dispatch_blobs`.
+    blob_dispatcher: JoinHandle<()>,
     num_nodes: usize,
+    // Payloads for a round other than the one currently being awaited, held
+    // here until `pmap` asks for that round.
+    round_blob_buffer: Vec<(u32, Vec<u8>)>,
+    // Set when `new` is given a shared secret; seals/opens every blob this
+    // node sends or receives. `None` keeps plaintext transport for clusters
+    // that don't opt in. Shared with `dispatch_blobs`, which opens inbound
+    // blobs independently of this struct.
+    cipher: Arc<Option<BlobCipher>>,
+    // The wire format used to move `Rower`s between nodes in `pmap`.
+    codec: C,
+    // Have/want bitfields for each dataframe this node is lazily pulling
+    // chunks of via `sync_chunks`.
+    chunk_sync: HashMap<String, ChunkSync>,
+    // Chunks `sync_chunks` has fetched, keyed by (df_name, chunk), readable
+    // via `get_chunk`.
+    chunks: HashMap<(String, usize), DataFrame>,
+    // Local subscribers registered via `subscribe`, drained by `publish`.
+    // A write made on a *different* node only reaches these if that node
+    // calls `notify_put`, which pushes a `Request::PutBlob` to every peer;
+    // `dispatch_blobs` routes it into `publish` on arrival, independent of
+    // any round-based call.
+    subscribers: Arc<RwLock<HashMap<Key, Vec<Sender<Vec<u8>>>>>>,
+    // Latency/size histograms for the hot paths in `pmap`/`map_allreduce`,
+    // read out via `metrics_snapshot`.
+    metrics: Metrics,
+    // Epoch-keyed memoization for `cache_get_or_init`.
+    cache: EpochCache,
 }
 
-impl Application {
+/// Default number of trailing epochs [`EpochCache`] keeps before evicting,
+/// overridable with [`Application::set_cache_window`].
+const DEFAULT_CACHE_WINDOW: Epoch = 2;
+
+/// How many [`Application::get_range`] partition fetches are allowed to be
+/// in flight at once, so a wide range fans out across nodes instead of
+/// serializing one `kv.get` after another, without the caller materializing
+/// the whole range the way collecting into a `Vec<DataFrame>` would.
+const GET_RANGE_CONCURRENCY: usize = 8;
+
+impl Application<Bincode> {
     pub async fn new(
         my_addr: &str,
         server_addr: &str,
         num_nodes: usize,
+        shared_secret: Option<&str>,
+    ) -> Result<Self, LiquidError> {
+        Self::new_with_codec(
+            my_addr,
+            server_addr,
+            num_nodes,
+            shared_secret,
+            Bincode,
+        )
+        .await
+    }
+
+    pub async fn from_sor(
+        file_name: &str,
+        my_addr: &str,
+        server_addr: &str,
+        num_nodes: usize,
+        shared_secret: Option<&str>,
+    ) -> Result<Self, LiquidError> {
+        Self::from_sor_with_codec(
+            file_name,
+            my_addr,
+            server_addr,
+            num_nodes,
+            shared_secret,
+            Bincode,
+        )
+        .await
+    }
+
+    /// Non-blocking counterpart to [`from_sor`](Application::from_sor):
+    /// stats the file with `tokio::fs` instead of `std::fs` and loads this
+    /// node's partition through [`DataFrame::from_sor_async`], so starting
+    /// many nodes against the same file doesn't stall any of their tokio
+    /// worker threads while the file is statted and parsed.
+    pub async fn from_sor_async(
+        file_name: &str,
+        my_addr: &str,
+        server_addr: &str,
+        num_nodes: usize,
+        shared_secret: Option<&str>,
+    ) -> Result<Self, LiquidError> {
+        Self::from_sor_async_with_codec(
+            file_name,
+            my_addr,
+            server_addr,
+            num_nodes,
+            shared_secret,
+            Bincode,
+        )
+        .await
+    }
+}
+
+impl<C: Codec> Application<C> {
+    /// Like [`from_sor`](Application::from_sor), but lets the caller pick
+    /// the wire format used to move `Rower`s between nodes instead of
+    /// defaulting to [`Bincode`], so a non-default-codec `Application`
+    /// still has a loader constructor.
+    pub async fn from_sor_with_codec(
+        file_name: &str,
+        my_addr: &str,
+        server_addr: &str,
+        num_nodes: usize,
+        shared_secret: Option<&str>,
+        codec: C,
+    ) -> Result<Self, LiquidError> {
+        let app = Self::new_with_codec(
+            my_addr,
+            server_addr,
+            num_nodes,
+            shared_secret,
+            codec,
+        )
+        .await?;
+        let file = std::fs::metadata(file_name).unwrap();
+        // Note: Node ids start at 1
+        // TODO: IMPORTANT ROUNDING ERRORS
+        let size = file.len() / num_nodes as u64;
+        let from = size * (app.node_id - 1) as u64;
+        let df = DataFrame::from_sor(
+            String::from(file_name),
+            from as usize,
+            size as usize,
+        );
+        let key = Key::new("420", app.node_id);
+        app.kv.put(&key, df).await?;
+        Ok(app)
+    }
+
+    /// Like [`from_sor_async`](Application::from_sor_async), but lets the
+    /// caller pick the wire format instead of defaulting to [`Bincode`].
+    pub async fn from_sor_async_with_codec(
+        file_name: &str,
+        my_addr: &str,
+        server_addr: &str,
+        num_nodes: usize,
+        shared_secret: Option<&str>,
+        codec: C,
+    ) -> Result<Self, LiquidError> {
+        let app = Self::new_with_codec(
+            my_addr,
+            server_addr,
+            num_nodes,
+            shared_secret,
+            codec,
+        )
+        .await?;
+        let meta = tokio::fs::metadata(file_name).await.unwrap();
+        // Note: Node ids start at 1
+        // TODO: IMPORTANT ROUNDING ERRORS
+        let size = meta.len() / num_nodes as u64;
+        let from = size * (app.node_id - 1) as u64;
+        let df = DataFrame::from_sor_async(
+            String::from(file_name),
+            from as usize,
+            size as usize,
+        )
+        .await;
+        let key = Key::new("420", app.node_id);
+        app.kv.put(&key, df).await?;
+        Ok(app)
+    }
+
+    /// Like [`new`](Application::new), but lets the caller pick the wire
+    /// format used to move `Rower`s between nodes instead of defaulting to
+    /// [`Bincode`].
+    pub async fn new_with_codec(
+        my_addr: &str,
+        server_addr: &str,
+        num_nodes: usize,
+        shared_secret: Option<&str>,
+        codec: C,
     ) -> Result<Self, LiquidError> {
         let notifier = Arc::new(Notify::new());
         let c = Client::<KVMessage>::new(
@@ -47,50 +720,363 @@ impl Application {
         let fut1 = tokio::spawn(async move {
             KVStore::process_messages(arc_new).await.unwrap();
         });
+
+        let cipher = Arc::new(shared_secret.map(BlobCipher::new));
+        let subscribers = Arc::new(RwLock::new(HashMap::new()));
+        let (round_sender, round_blob_receiver) = channel(16);
+        let fut2 = tokio::spawn(dispatch_blobs(
+            blob_receiver,
+            cipher.clone(),
+            subscribers.clone(),
+            round_sender,
+        ));
+
         Ok(Application {
             kv: kv_arc,
             node_id,
-            blob_receiver,
+            round_blob_receiver,
             msg_processor: fut1,
             conn_processor: fut0,
+            blob_dispatcher: fut2,
             num_nodes,
+            round_blob_buffer: Vec::new(),
+            cipher,
+            codec,
+            chunk_sync: HashMap::new(),
+            chunks: HashMap::new(),
+            subscribers,
+            metrics: Metrics::new(),
+            cache: EpochCache::new(DEFAULT_CACHE_WINDOW),
         })
     }
 
-    pub async fn from_sor(
-        file_name: &str,
-        my_addr: &str,
-        server_addr: &str,
-        num_nodes: usize,
-    ) -> Result<Self, LiquidError> {
-        let app = Application::new(my_addr, server_addr, num_nodes).await?;
-        let file = std::fs::metadata(file_name).unwrap();
-        // Note: Node ids start at 1
-        // TODO: IMPORTANT ROUNDING ERRORS
-        let size = file.len() / num_nodes as u64;
-        let from = size * (app.node_id - 1) as u64;
-        let df = DataFrame::from_sor(
-            String::from(file_name),
-            from as usize,
-            size as usize,
+    /// Seals `plaintext` with [`cipher`](Application::cipher) when this
+    /// cluster was started with a shared secret, otherwise returns it
+    /// unchanged.
+    fn seal_blob(&self, plaintext: Vec<u8>) -> Vec<u8> {
+        match &*self.cipher {
+            Some(cipher) => cipher.seal(&plaintext),
+            None => plaintext,
+        }
+    }
+
+    /// Streams the partitions owned by every node in `from_node..=to_node`
+    /// under the given `key_prefix`, in node order, fanning up to
+    /// [`GET_RANGE_CONCURRENCY`] of those `kv.get`s out at once instead of
+    /// awaiting them one at a time. This lets a caller run a `Rower` over
+    /// an arbitrary subset of the cluster without materializing every
+    /// partition in memory at once the way collecting a `Vec<DataFrame>`
+    /// up front would, while still overlapping the cross-node fetches the
+    /// way [`put_batch`](Application::put_batch) does for writes.
+    pub fn get_range<'a>(
+        &'a self,
+        key_prefix: &'a str,
+        from_node: usize,
+        to_node: usize,
+    ) -> impl Stream<Item = Result<DataFrame, LiquidError>> + 'a {
+        stream::iter(from_node..=to_node)
+            .map(move |node_id| {
+                let key = Key::new(key_prefix, node_id);
+                async move { self.kv.get(&key).await }
+            })
+            .buffered(GET_RANGE_CONCURRENCY)
+    }
+
+    /// Writes `dataframes[i]` under `Key::new(key_prefix, from_node + i)`
+    /// for each partition, fanning the puts out concurrently across the
+    /// owning nodes instead of sequentially awaiting one `kv.put` at a
+    /// time.
+    pub async fn put_batch(
+        &self,
+        key_prefix: &str,
+        from_node: usize,
+        dataframes: Vec<DataFrame>,
+    ) -> Result<(), LiquidError> {
+        let futs = dataframes
+            .into_iter()
+            .enumerate()
+            .map(|(i, df)| {
+                self.kv.put(&Key::new(key_prefix, from_node + i), df)
+            });
+        try_join_all(futs).await?;
+        Ok(())
+    }
+
+    /// Blocks until every chunk set in `wanted` (one bit per 0-based
+    /// partition of `df_name`) is present in this node's local `ChunkSync`
+    /// bookkeeping, looping
+    /// [`sync_chunks_with_peer`](Application::sync_chunks_with_peer) over
+    /// every other node until `wanted` is fully resolved, sleeping
+    /// [`SYNC_CHUNKS_RETRY_BACKOFF`] between passes so a peer that hasn't
+    /// loaded its partition yet doesn't get hammered. Gives up with
+    /// `LiquidError::TimedOut` after [`SYNC_CHUNKS_MAX_PASSES`] passes.
+    /// Lives on `Application` rather than `kv.sync_chunks` since `KVStore`
+    /// isn't part of this source tree.
+    pub async fn sync_chunks(
+        &mut self,
+        df_name: &str,
+        wanted: BitVec,
+    ) -> Result<(), LiquidError> {
+        let own_chunk = self.node_id - 1;
+        for pass in 0..=SYNC_CHUNKS_MAX_PASSES {
+            let have = self
+                .chunk_sync
+                .entry(df_name.to_string())
+                .or_insert_with(|| ChunkSync::new(wanted.len(), own_chunk))
+                .have
+                .clone();
+            let mut missing = wanted.clone();
+            missing &= !have;
+            if missing.not_any() {
+                return Ok(());
+            }
+            if pass == SYNC_CHUNKS_MAX_PASSES {
+                break;
+            }
+            if pass > 0 {
+                sleep(SYNC_CHUNKS_RETRY_BACKOFF).await;
+            }
+            for peer_idx in 0..self.num_nodes {
+                if peer_idx == own_chunk {
+                    continue;
+                }
+                self.sync_chunks_with_peer(df_name, wanted.clone(), peer_idx)
+                    .await?;
+            }
+        }
+        Err(LiquidError::TimedOut)
+    }
+
+    /// A single have/want exchange with the node at 0-based index
+    /// `peer_idx`, fetching only the chunks that peer reports having;
+    /// wanted chunks that peer doesn't have are not recorded in `ChunkSync`
+    /// and are left to a later call. Not a blocking wait for `wanted` to fully resolve -- call
+    /// [`sync_chunks`](Application::sync_chunks) for that.
+    async fn sync_chunks_with_peer(
+        &mut self,
+        df_name: &str,
+        wanted: BitVec,
+        peer_idx: usize,
+    ) -> Result<(), LiquidError> {
+        let n_chunks = wanted.len();
+        let own_chunk = self.node_id - 1;
+        let my_have = self
+            .chunk_sync
+            .entry(df_name.to_string())
+            .or_insert_with(|| ChunkSync::new(n_chunks, own_chunk))
+            .have
+            .clone();
+
+        let exchange = ChunkSyncMessage {
+            df_name: df_name.to_string(),
+            have: my_have,
+        };
+        let round =
+            chunk_sync_round(own_chunk, peer_idx, self.num_nodes, df_name);
+        let peer: ChunkSyncMessage =
+            self.exchange_round(round, peer_idx, &exchange, true).await?;
+        debug_assert_eq!(
+            peer.df_name, df_name,
+            "chunk sync round collided across two different dataframes \
+             (df_name hash collision in chunk_sync_round)"
         );
-        let key = Key::new("420", app.node_id);
-        app.kv.put(&key, df).await?;
-        Ok(app)
+
+        let mut peer_has_and_wanted = wanted;
+        peer_has_and_wanted &= peer.have;
+
+        let to_fetch = self
+            .chunk_sync
+            .get_mut(df_name)
+            .expect("inserted above")
+            .chunks_to_fetch(&peer_has_and_wanted);
+
+        for chunk in to_fetch {
+            // `chunk` is a 0-based partition index; partitions are keyed
+            // by the 1-based node id that owns them.
+            let df = self.kv.get(&Key::new(df_name, chunk + 1)).await?;
+            self.chunks.insert((df_name.to_string(), chunk), df);
+            self.chunk_sync
+                .get_mut(df_name)
+                .expect("inserted above")
+                .on_chunk_arrived(chunk);
+        }
+        Ok(())
     }
 
+    /// Returns this node's locally stored copy of `chunk` (0-based
+    /// partition index) of `df_name`, previously pulled in by
+    /// [`sync_chunks`](Application::sync_chunks).
+    pub fn get_chunk(&self, df_name: &str, chunk: usize) -> Option<&DataFrame> {
+        self.chunks.get(&(df_name.to_string(), chunk))
+    }
+
+    /// Notifies every other node that `data` was just written under `key`,
+    /// then [`publish`](Application::publish)es locally. Meant to be called
+    /// right after a `kv.put` under the same `key`: `kv.put` has no way of
+    /// its own to cross into another node's `subscribers` map, so a caller
+    /// whose write should reach remote subscribers needs this extra step.
+    /// Each peer gets a [`Request::PutBlob`] over the same wire channel
+    /// `pmap`/`map_allreduce` use for round blobs, routed into that peer's
+    /// `publish` by its `dispatch_blobs` background task.
+    pub async fn notify_put(
+        &mut self,
+        key: Key,
+        data: Vec<u8>,
+    ) -> Result<(), LiquidError> {
+        for peer in 1..=self.num_nodes {
+            if peer == self.node_id {
+                continue;
+            }
+            let envelope = Envelope::Request(Request::PutBlob {
+                key: key.clone(),
+                data: data.clone(),
+            });
+            let blob = self.seal_blob(serialize(&envelope)?);
+            self.kv.send_blob(peer, blob).await?;
+        }
+        self.publish(&key, data).await
+    }
+
+    /// Writes `value` under `key` via `kv.put`, then
+    /// [`notify_put`](Application::notify_put)s the same bytes so every
+    /// subscriber sees the write. The single call a writer actually wants:
+    /// a bare `kv.put` never reaches [`subscribe`](Application::subscribe)rs
+    /// on another node, and splitting the two steps leaves room for a
+    /// caller to write without remembering to notify.
+    pub async fn put_and_notify<T: Serialize>(
+        &mut self,
+        key: Key,
+        value: T,
+    ) -> Result<(), LiquidError> {
+        let data = serialize(&value)?;
+        self.kv.put(&key, value).await?;
+        self.notify_put(key, data).await
+    }
+
+    /// Registers interest in `key` and returns a channel that receives a
+    /// push every time [`publish`](Application::publish) is called for
+    /// that key. Multiple subscribers may register for the same key; each
+    /// gets its own copy of every push. A remote write reaches this
+    /// independent of whether any round-based call is active, since
+    /// `dispatch_blobs` drains `Request::PutBlob` on its own background
+    /// task.
     ///
+    /// Lives on `Application` rather than `kv.subscribe`, for the same
+    /// reason [`sync_chunks`](Application::sync_chunks) does: `KVStore`
+    /// isn't part of this source tree.
     ///
-    /// NOTE:
-    ///
-    /// There is an important design decision that comes with a distinct trade
-    /// off here. The trade off is:
-    /// 1. Join the last node with the next one until you get to the end. This
-    ///    has reduced memory requirements but a performance impact because
-    ///    of the synchronous network calls
-    /// 2. Join all nodes with one node. This has increased memory requirements
-    ///    but greater performance because all nodes can asynchronously send
-    ///    to the joiner at one time.
+    /// A remote subscriber only sees a write made via
+    /// [`put_and_notify`](Application::put_and_notify) (or followed by
+    /// [`notify_put`](Application::notify_put)); a bare `kv.put`
+    /// never crosses into this map.
+    pub async fn subscribe(&self, key: Key) -> Receiver<Vec<u8>> {
+        let (tx, rx) = channel(16);
+        self.subscribers
+            .write()
+            .await
+            .entry(key)
+            .or_insert_with(Vec::new)
+            .push(tx);
+        rx
+    }
+
+    /// Fans `data` out to every channel registered for `key` via
+    /// [`subscribe`](Application::subscribe), handling a
+    /// [`Request::PutBlob`] for a key with active subscribers. Subscribers
+    /// whose receiver has already been dropped are pruned.
+    pub async fn publish(
+        &self,
+        key: &Key,
+        data: Vec<u8>,
+    ) -> Result<(), LiquidError> {
+        dispatch_publish(&self.subscribers, key, data).await;
+        Ok(())
+    }
+
+    /// Returns the cached value for `(epoch, key)`, constructing it with
+    /// `init_fn` on the first call for that pair and memoizing the result
+    /// for every later call, e.g. across the per-`degrees` iterations of
+    /// an algorithm that otherwise re-deserializes the same blob or
+    /// re-reads the same dataframe segment on every pass.
+    pub async fn cache_get_or_init<T, F, Fut>(
+        &self,
+        epoch: Epoch,
+        key: &str,
+        init_fn: F,
+    ) -> Arc<T>
+    where
+        T: Any + Send + Sync,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        self.cache.get_or_init(epoch, key, init_fn).await
+    }
+
+    /// Changes how many trailing epochs [`cache_get_or_init`](Application::cache_get_or_init)
+    /// keeps before evicting older generations. Defaults to
+    /// [`DEFAULT_CACHE_WINDOW`].
+    pub fn set_cache_window(&mut self, window: Epoch) {
+        self.cache.window = window;
+    }
+
+    /// Merges this node's [`Metrics`] across the whole cluster via the same
+    /// recursive-doubling all-reduce [`map_allreduce`](Application::map_allreduce)
+    /// uses (see `allreduce_plan`), so every node gets the combined
+    /// [`MetricsReport`] back, not just node 0. Round numbers are offset by
+    /// [`METRICS_ROUND_BASE`] so they don't collide with a `pmap`/
+    /// `map_allreduce`/`sync_chunks` round over the same `blob_receiver`.
+    pub async fn metrics_snapshot(
+        &mut self,
+    ) -> Result<MetricsReport, LiquidError> {
+        let mut bundle = MetricsBundle {
+            local_map: self.metrics.local_map.snapshot(),
+            join: self.metrics.join.snapshot(),
+            exchange: self.metrics.exchange.snapshot(),
+            blob_bytes: self.metrics.blob_bytes.snapshot(),
+        };
+
+        let idx = self.node_id - 1;
+        let (pow2, extra, doubling_rounds) = allreduce_plan(self.num_nodes);
+        let fold_in_round = METRICS_ROUND_BASE;
+        let fold_out_round = METRICS_ROUND_BASE + doubling_rounds + 1;
+
+        if idx >= pow2 {
+            self.send_round(fold_in_round, idx - pow2, &bundle, false)
+                .await?;
+        } else if idx < extra {
+            let external: MetricsBundle =
+                self.recv_round(fold_in_round, false).await?;
+            bundle.merge(&external);
+        }
+
+        if idx < pow2 {
+            for k in 0..doubling_rounds {
+                let step = 1usize << k;
+                let partner = idx ^ step;
+                let round = METRICS_ROUND_BASE + k + 1;
+                let external: MetricsBundle = self
+                    .exchange_round(round, partner, &bundle, false)
+                    .await?;
+                bundle.merge(&external);
+            }
+        }
+
+        if idx < extra {
+            self.send_round(fold_out_round, idx + pow2, &bundle, false)
+                .await?;
+        } else if idx >= pow2 {
+            bundle = self.recv_round(fold_out_round, false).await?;
+        }
+
+        Ok(bundle.into_report())
+    }
+
+    /// Combines the per-node results of mapping `rower` over this node's
+    /// partition (the one `from_sor` stored under key `"420"`; `df_name` is
+    /// not used to look it up) using a tree reduction with O(log `num_nodes`) rounds,
+    /// rather than draining a linear chain through node 1. See
+    /// `tree_reduce_role` for what happens in each round; only index 0 gets
+    /// `Some` back, once `2^k >= num_nodes`.
     pub async fn pmap<R>(
         &mut self,
         df_name: &str,
@@ -99,29 +1085,185 @@ impl Application {
     where
         R: Rower + Serialize + DeserializeOwned + Send + Clone,
     {
-        println!("{}", df_name);
-        match self.kv.get(&Key::new("420", self.node_id)).await {
-            Ok(df) => {
-                let mut res = df.pmap(rower);
-                if self.node_id != self.num_nodes {
-                    // we are the last node
-                    let blob = serialize(&res)?;
-                    self.kv.send_blob(self.node_id - 1, blob).await?;
-                    Ok(None)
-                } else {
-                    let mut blob = self.blob_receiver.recv().await.unwrap();
-                    let external_rower: R = deserialize(&blob[..])?;
-                    res = res.join(&external_rower);
-                    if self.node_id != 1 {
-                        blob = serialize(&res)?;
-                        self.kv.send_blob(self.node_id - 1, blob).await?;
-                        Ok(None)
-                    } else {
-                        Ok(Some(res))
-                    }
+        let df = self.kv.get(&Key::new("420", self.node_id)).await?;
+        let map_start = Instant::now();
+        let mut res = df.pmap(rower);
+        self.metrics.local_map.record(map_start.elapsed().as_nanos() as u64);
+
+        let idx = self.node_id - 1;
+        let mut k: u32 = 0;
+        while (1usize << k) < self.num_nodes {
+            match tree_reduce_role(idx, k, self.num_nodes) {
+                TreeRole::Recv { .. } => {
+                    let external: R = self.recv_round(k, true).await?;
+                    let join_start = Instant::now();
+                    res = res.join(external);
+                    self.metrics.join.record(join_start.elapsed().as_nanos() as u64);
+                }
+                TreeRole::Send { partner } => {
+                    self.send_round(k, partner, &res, true).await?;
+                    return Ok(None);
                 }
+                TreeRole::Carry => {}
+            }
+            k += 1;
+        }
+
+        if idx == 0 {
+            Ok(Some(res))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the fully combined `Rower` on *every* node instead of only
+    /// on node 0, via a recursive-doubling all-reduce over `Rower::join`
+    /// (must be associative and commutative, since nodes combine values in
+    /// whatever order their exchanges land). See `allreduce_plan` for how
+    /// non-power-of-2 node counts are folded in and back out.
+    pub async fn map_allreduce<R>(
+        &mut self,
+        df_name: &str,
+        rower: R,
+    ) -> Result<R, LiquidError>
+    where
+        R: Rower + Serialize + DeserializeOwned + Send + Clone,
+    {
+        let df = self.kv.get(&Key::new("420", self.node_id)).await?;
+        let map_start = Instant::now();
+        let mut res = df.pmap(rower);
+        self.metrics.local_map.record(map_start.elapsed().as_nanos() as u64);
+
+        let idx = self.node_id - 1;
+        let (pow2, extra, doubling_rounds) = allreduce_plan(self.num_nodes);
+        let fold_in_round: u32 = 0;
+        let fold_out_round = doubling_rounds + 1;
+
+        if idx >= pow2 {
+            self.send_round(fold_in_round, idx - pow2, &res, true).await?;
+        } else if idx < extra {
+            let other: R = self.recv_round(fold_in_round, true).await?;
+            let join_start = Instant::now();
+            res = res.join(other);
+            self.metrics.join.record(join_start.elapsed().as_nanos() as u64);
+        }
+
+        if idx < pow2 {
+            for k in 0..doubling_rounds {
+                let step = 1usize << k;
+                let partner = idx ^ step;
+                let round = k + 1;
+                let other: R = self
+                    .exchange_round(round, partner, &res, true)
+                    .await?;
+                let join_start = Instant::now();
+                res = res.join(other);
+                self.metrics.join.record(join_start.elapsed().as_nanos() as u64);
+            }
+        }
+
+        if idx < extra {
+            self.send_round(fold_out_round, idx + pow2, &res, true).await?;
+        } else if idx >= pow2 {
+            res = self.recv_round(fold_out_round, true).await?;
+        }
+
+        Ok(res)
+    }
+
+    /// Encodes and seals `res` before sending it as the blob for `round`
+    /// to the node at 0-based index `partner_idx`. Records into
+    /// `self.metrics` unless `record` is false -- [`metrics_snapshot`]
+    /// passes false so its own reporting round doesn't inflate the very
+    /// histograms it's reporting on.
+    async fn send_round<R>(
+        &mut self,
+        round: u32,
+        partner_idx: usize,
+        res: &R,
+        record: bool,
+    ) -> Result<(), LiquidError>
+    where
+        R: Serialize,
+    {
+        let start = Instant::now();
+        let payload = self.codec.encode(res)?;
+        if record {
+            self.metrics.blob_bytes.record(payload.len() as u64);
+        }
+        let envelope = Envelope::Round(RoundBlob { round, payload });
+        let blob = self.seal_blob(serialize(&envelope)?);
+        self.kv.send_blob(partner_idx + 1, blob).await?;
+        if record {
+            self.metrics.exchange.record(start.elapsed().as_nanos() as u64);
+        }
+        Ok(())
+    }
+
+    /// Waits for and decodes the blob tagged with the given `round`. See
+    /// [`send_round`](Application::send_round) for what `record` does.
+    async fn recv_round<R>(
+        &mut self,
+        round: u32,
+        record: bool,
+    ) -> Result<R, LiquidError>
+    where
+        R: DeserializeOwned,
+    {
+        let start = Instant::now();
+        let payload = self.recv_round_blob(round).await?;
+        let decoded = self.codec.decode(&payload)?;
+        if record {
+            self.metrics.exchange.record(start.elapsed().as_nanos() as u64);
+        }
+        Ok(decoded)
+    }
+
+    /// Sends `res` to `partner_idx` for `round` and returns what that node
+    /// sent back for the same round. See
+    /// [`send_round`](Application::send_round) for what `record` does.
+    async fn exchange_round<R>(
+        &mut self,
+        round: u32,
+        partner_idx: usize,
+        res: &R,
+        record: bool,
+    ) -> Result<R, LiquidError>
+    where
+        R: Serialize + DeserializeOwned,
+    {
+        self.send_round(round, partner_idx, res, record).await?;
+        self.recv_round(round, record).await
+    }
+
+    /// Waits for the payload tagged with the given reduction `round`,
+    /// buffering any payloads for other rounds that arrive first so a later
+    /// call can pick them up. Round-tagged payloads are handed to
+    /// `round_blob_receiver` by `dispatch_blobs`, which also handles the
+    /// sealing/opening and any non-round `Request` envelopes -- this is
+    /// purely the round-buffering half of that split. A blob `dispatch_blobs`
+    /// couldn't open or decode arrives as an `Err` with no round attached;
+    /// since there's no way to tell whether it was meant for `round` or some
+    /// other in-flight call, it's propagated immediately rather than risking
+    /// this loop spinning forever waiting for a payload that will never
+    /// arrive.
+    async fn recv_round_blob(
+        &mut self,
+        round: u32,
+    ) -> Result<Vec<u8>, LiquidError> {
+        if let Some(pos) =
+            self.round_blob_buffer.iter().position(|(r, _)| *r == round)
+        {
+            let (_, payload) = self.round_blob_buffer.remove(pos);
+            return Ok(payload);
+        }
+        loop {
+            let (r, payload) =
+                self.round_blob_receiver.recv().await.unwrap()?;
+            if r == round {
+                return Ok(payload);
             }
-            Err(e) => Err(e),
+            self.round_blob_buffer.push((r, payload));
         }
     }
 
@@ -137,5 +1279,249 @@ impl Application {
     pub async fn go(self) {
         self.msg_processor.await.unwrap();
         self.conn_processor.await.unwrap();
+        self.blob_dispatcher.await.unwrap();
+    }
+}
+
+/// Opens a blob sealed by [`Application::seal_blob`], or returns it
+/// unchanged if this cluster isn't encrypting transport.
+fn open_sealed_blob(
+    cipher: &Option<BlobCipher>,
+    blob: Value,
+) -> Result<Vec<u8>, LiquidError> {
+    match cipher {
+        Some(cipher) => cipher.open(&blob),
+        None => Ok(blob.to_vec()),
+    }
+}
+
+/// Fans `data` out to every channel registered for `key` in `subscribers`,
+/// pruning any whose receiver has already been dropped. The logic behind
+/// both [`Application::publish`] and [`dispatch_blobs`]'s handling of a
+/// [`Request::PutBlob`].
+async fn dispatch_publish(
+    subscribers: &RwLock<HashMap<Key, Vec<Sender<Vec<u8>>>>>,
+    key: &Key,
+    data: Vec<u8>,
+) {
+    let mut subscribers = subscribers.write().await;
+    if let Some(senders) = subscribers.get_mut(key) {
+        let mut live = Vec::with_capacity(senders.len());
+        for tx in senders.drain(..) {
+            if tx.send(data.clone()).await.is_ok() {
+                live.push(tx);
+            }
+        }
+        *senders = live;
+    }
+}
+
+/// Runs for the `Application`'s lifetime, independent of whatever
+/// round-based call (`pmap`/`map_allreduce`/`metrics_snapshot`/
+/// `sync_chunks`) may or may not currently be active: opens and decodes
+/// every blob off the raw wire channel, forwards round-tagged payloads to
+/// `round_sender` for [`Application::recv_round_blob`] to pick up, and
+/// dispatches a [`Request::PutBlob`] straight to [`dispatch_publish`] so
+/// [`Application::subscribe`] works whether or not a round-based call
+/// happens to be running concurrently. A blob that fails to open or decode
+/// can't be matched to the round it was meant for, so its `LiquidError` is
+/// sent down `round_sender` instead -- otherwise whatever round-based call
+/// is waiting would block on `recv_round_blob` forever.
+async fn dispatch_blobs(
+    mut blob_receiver: Receiver<Value>,
+    cipher: Arc<Option<BlobCipher>>,
+    subscribers: Arc<RwLock<HashMap<Key, Vec<Sender<Vec<u8>>>>>>,
+    round_sender: Sender<Result<(u32, Vec<u8>), LiquidError>>,
+) {
+    while let Some(blob) = blob_receiver.recv().await {
+        let opened = match open_sealed_blob(&cipher, blob) {
+            Ok(opened) => opened,
+            Err(e) => {
+                if round_sender.send(Err(e)).await.is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+        let envelope: Envelope = match deserialize(&opened[..]) {
+            Ok(envelope) => envelope,
+            Err(_) => {
+                if round_sender
+                    .send(Err(LiquidError::EncodingFailed))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                continue;
+            }
+        };
+        match envelope {
+            Envelope::Round(round_blob) => {
+                if round_sender
+                    .send(Ok((round_blob.round, round_blob.payload)))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Envelope::Request(Request::PutBlob { key, data }) => {
+                dispatch_publish(&subscribers, &key, data).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_cipher_seal_open_round_trips() {
+        let cipher = BlobCipher::new("shared secret");
+        let plaintext = b"some dataframe bytes".to_vec();
+        let sealed = cipher.seal(&plaintext);
+        assert_ne!(sealed, plaintext);
+        assert_eq!(cipher.open(&sealed).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_blob_cipher_rejects_truncated_input() {
+        let cipher = BlobCipher::new("shared secret");
+        assert!(cipher.open(&[0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn test_blob_cipher_rejects_tampered_ciphertext() {
+        let cipher = BlobCipher::new("shared secret");
+        let mut sealed = cipher.seal(b"hello");
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0xff;
+        assert!(cipher.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_histogram_percentile_tracks_recorded_buckets() {
+        let hist = Histogram::new();
+        // 1 falls in bucket 1 ([1, 2)); 2 in bucket 2 ([2, 4)); 4 falls
+        // in bucket 3 ([4, 8)), reported as that bucket's upper edge.
+        for value in [1u64, 2, 2, 4, 4, 4, 4] {
+            hist.record(value);
+        }
+        let snapshot = hist.snapshot();
+        assert_eq!(snapshot.percentile(0.5), 8);
+        assert_eq!(snapshot.percentile(0.99), 8);
+
+        let single = Histogram::new();
+        single.record(1);
+        assert_eq!(single.snapshot().percentile(0.5), 2);
+    }
+
+    #[test]
+    fn test_histogram_snapshot_merge_sums_bucket_counts() {
+        let a = Histogram::new();
+        a.record(1);
+        let b = Histogram::new();
+        b.record(1);
+        b.record(1);
+        let mut merged = a.snapshot();
+        merged.merge(&b.snapshot());
+        // 3 total observations of 1, all in the same bucket.
+        assert_eq!(merged.percentile(1.0), 2);
+        assert_eq!(merged.0.iter().sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn test_empty_histogram_percentile_is_zero() {
+        let hist = Histogram::new();
+        assert_eq!(hist.snapshot().percentile(0.5), 0);
+    }
+
+    #[test]
+    fn test_tree_reduce_role_matches_pmap_semantics() {
+        // 5 nodes: round 0 pairs (0,1), (2,3); 4 has no partner and
+        // carries. Round 1 pairs (0,2) -- the winner of (2,3); 4 still
+        // carries (4 + 2 = 6 is out of range). Round 2 pairs (0,4), the
+        // last node standing, finishing the reduction at node 0.
+        assert_eq!(tree_reduce_role(0, 0, 5), TreeRole::Recv { partner: 1 });
+        assert_eq!(tree_reduce_role(1, 0, 5), TreeRole::Send { partner: 0 });
+        assert_eq!(tree_reduce_role(2, 0, 5), TreeRole::Recv { partner: 3 });
+        assert_eq!(tree_reduce_role(3, 0, 5), TreeRole::Send { partner: 2 });
+        assert_eq!(tree_reduce_role(4, 0, 5), TreeRole::Carry);
+
+        assert_eq!(tree_reduce_role(0, 1, 5), TreeRole::Recv { partner: 2 });
+        assert_eq!(tree_reduce_role(2, 1, 5), TreeRole::Send { partner: 0 });
+        assert_eq!(tree_reduce_role(4, 1, 5), TreeRole::Carry);
+
+        assert_eq!(tree_reduce_role(0, 2, 5), TreeRole::Recv { partner: 4 });
+        assert_eq!(tree_reduce_role(4, 2, 5), TreeRole::Send { partner: 0 });
+    }
+
+    #[test]
+    fn test_allreduce_plan_folds_extras_into_largest_power_of_two() {
+        assert_eq!(allreduce_plan(8), (8, 0, 3));
+        assert_eq!(allreduce_plan(5), (4, 1, 2));
+        assert_eq!(allreduce_plan(1), (1, 0, 0));
+        assert_eq!(allreduce_plan(6), (4, 2, 2));
+        assert_eq!(allreduce_plan(7), (4, 3, 2));
+    }
+
+    #[test]
+    fn test_chunk_sync_chunks_to_fetch_skips_had_and_in_flight() {
+        // Own chunk is 0, which `wanted` never asks for, so `have` being
+        // pre-seeded with it doesn't change the expected fetch lists below.
+        let mut sync = ChunkSync::new(4, 0);
+
+        // Nothing held or in flight yet: every wanted chunk is fetched, and
+        // the call marks them all as requested.
+        let wanted = bitvec![0, 1, 1, 0];
+        assert_eq!(sync.chunks_to_fetch(&wanted), vec![1, 2]);
+
+        // A second call for the same chunks returns nothing new: they're
+        // already in flight (`requested`), not yet resolved.
+        assert!(sync.chunks_to_fetch(&wanted).is_empty());
+
+        // Once chunk 1 arrives, it drops out of `want`/`requested` and
+        // asking for it again is a no-op; a newly wanted chunk is returned.
+        sync.on_chunk_arrived(1);
+        let wanted = bitvec![0, 1, 0, 1];
+        assert_eq!(sync.chunks_to_fetch(&wanted), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_epoch_cache_memoizes_and_evicts_old_generations() {
+        let cache = EpochCache::new(2);
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let calls_clone = calls.clone();
+        let v1 = cache
+            .get_or_init(0, "k", || async move {
+                calls_clone.fetch_add(1, Ordering::Relaxed);
+                42usize
+            })
+            .await;
+        assert_eq!(*v1, 42);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // Same (epoch, key): cached, `init` doesn't run again.
+        let calls_clone = calls.clone();
+        let v2 = cache
+            .get_or_init(0, "k", || async move {
+                calls_clone.fetch_add(1, Ordering::Relaxed);
+                99usize
+            })
+            .await;
+        assert_eq!(*v2, 42);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // Epochs 1, 2 keep epoch 0 within the window of 2.
+        cache.get_or_init(1, "k", || async { 1usize }).await;
+        cache.get_or_init(2, "k", || async { 2usize }).await;
+        assert!(cache.lookup::<usize>(0, "k").await.is_some());
+
+        // Epoch 3 pushes epoch 0 outside the window; it's evicted.
+        cache.get_or_init(3, "k", || async { 3usize }).await;
+        assert!(cache.lookup::<usize>(0, "k").await.is_none());
     }
 }
\ No newline at end of file
@@ -13,6 +13,7 @@ use num_cpus;
 use sorer::dataframe::{from_file, Column, Data};
 use sorer::schema::{infer_schema_from_file, DataType};
 use std::thread;
+use tokio::task;
 
 /// Represents a DataFrame which contains
 /// [columnar](sorer::dataframe::Column) `Data` and a
@@ -42,6 +43,60 @@ impl DataFrame {
         }
     }
 
+    /// Non-blocking, chunked counterpart to [`from_sor`](DataFrame::from_sor):
+    /// parses the `[from, from + len)` byte window in bounded-size chunks,
+    /// each on the `tokio` blocking thread pool, instead of one blocking
+    /// `from_file` call for the whole window. This keeps a node's tokio
+    /// worker threads unblocked while its partition is parsed; it does not
+    /// reduce peak memory use below [`from_sor`](DataFrame::from_sor), since
+    /// the per-chunk `Column`s are still stitched back together into one
+    /// in-memory `data` before returning.
+    pub async fn from_sor_async(
+        file_name: String,
+        from: usize,
+        len: usize,
+    ) -> Self {
+        const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+        let schema = {
+            let name = file_name.clone();
+            task::spawn_blocking(move || Schema::from(infer_schema_from_file(name)))
+                .await
+                .expect("infer_schema_from_file task panicked")
+        };
+
+        // Always runs at least once, even when `len == 0`: `from_sor` (sync)
+        // always calls `from_file` once regardless of window size, and a
+        // zero-length window still needs one empty-column `Column` per
+        // schema entry, not an empty `data` with the wrong column count.
+        let mut chunk_tasks = Vec::new();
+        let mut offset = from;
+        let end = from + len;
+        loop {
+            let chunk_len = CHUNK_SIZE.min(end - offset);
+            let name = file_name.clone();
+            let types = schema.schema.clone();
+            chunk_tasks.push(task::spawn_blocking(move || {
+                from_file(name, types, offset, chunk_len)
+            }));
+            offset += chunk_len;
+            if offset >= end {
+                break;
+            }
+        }
+
+        let mut data: Vec<Column> = Vec::new();
+        for chunk_task in chunk_tasks {
+            let cols = chunk_task.await.expect("from_sor chunk task panicked");
+            append_chunk_columns(&mut data, cols, schema.schema.len());
+        }
+
+        DataFrame {
+            schema,
+            data,
+            n_threads: num_cpus::get(),
+        }
+    }
+
     /// Creates an empty `DataFrame` from the given
     /// [`Schema`](::crate::schema::Schema).
     pub fn new(s: Schema) -> Self {
@@ -239,43 +294,56 @@ impl DataFrame {
     }
 
     pub fn map<T: Rower>(&self, rower: &mut T) {
-        map_helper(self, rower, 0, self.nrows());
+        map_helper(self, rower, 0, self.n_rows());
     }
 
-    // NOTE: crossbeam might remove the 'static
-    /*pub fn pmap<T: Rower + Clone + Send>(&'static self, rower: &'static mut T) {
-        //let mut rowers = Vec::new();
-        let mut threads = Vec::new();
-        //for _ in 0..self.n_threads - 1 {
-        //    rowers.push(&mut rower.clone());
-        //}
-        //rowers.insert(0, rower);
-
-        let rowers = vec![*rower; self.n_threads];
-        let step = self.nrows() / self.n_threads; // +1 for this thread
-        let mut from = 0;
-        for i in 0..self.n_threads - 1 {
-            threads.push(thread::spawn(move || {
-                map_helper::<T>(&self, rowers.get_mut(i).unwrap(), from, from + step)
-            }));
-            from += step;
+    /// The node-local, multi-threaded half of `Application::pmap`: splits
+    /// `0..n_rows()` into `n_threads` contiguous ranges, clones `rower`
+    /// once per range, and runs each range's `map_helper` on its own
+    /// scoped thread so the clones can borrow `self` directly instead of
+    /// requiring a `'static` bound. The per-thread rowers are then folded
+    /// back together in ascending range order via `Rower::join`. Falls
+    /// back to the single-threaded `map` when there are fewer rows than
+    /// threads, since splitting wouldn't give every thread any work.
+    pub fn pmap<T: Rower + Clone + Send>(&self, rower: T) -> T {
+        let n_rows = self.n_rows();
+        if self.n_threads == 0 || n_rows < self.n_threads {
+            let mut rower = rower;
+            map_helper(self, &mut rower, 0, n_rows);
+            return rower;
         }
 
-        map_helper::<T>(
-            self,
-            rowers.get_mut(self.n_threads).unwrap(),
-            from,
-            self.nrows(),
-        );
-
-        for thread in threads {
-            thread.join().unwrap();
+        let step = n_rows / self.n_threads;
+        let mut ranges = Vec::with_capacity(self.n_threads);
+        let mut from = 0;
+        for i in 0..self.n_threads {
+            let to = if i == self.n_threads - 1 {
+                n_rows
+            } else {
+                from + step
+            };
+            ranges.push((from, to));
+            from = to;
         }
 
-        //for (i, r) in rowers.iter_mut().enumerate().rev().skip(1) {
-        //    r.join(rowers.get_mut(i + 1).unwrap());
-        //}
-    }*/
+        let rowers: Vec<T> = thread::scope(|scope| {
+            ranges
+                .iter()
+                .map(|&(from, to)| {
+                    let mut r = rower.clone();
+                    scope.spawn(move || {
+                        map_helper(self, &mut r, from, to);
+                        r
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        rowers.into_iter().reduce(|acc, r| acc.join(r)).unwrap()
+    }
 
     /// Return the number of rows in this `DataFrame`.
     pub fn n_rows(&self) -> usize {
@@ -301,3 +369,112 @@ fn map_helper<T: Rower>(
         rower.visit(&mut row);
     }
 }
+
+/// Appends `cols` onto `data` column-by-column, or takes `cols` as-is if
+/// `data` is still empty (the first chunk). Used by `from_sor_async` to
+/// stitch its per-chunk parses back into one set of columns in order.
+/// Panics if `cols` doesn't have `n_schema_cols` columns, or if a column's
+/// type doesn't match the column already accumulated at that index.
+fn append_chunk_columns(
+    data: &mut Vec<Column>,
+    cols: Vec<Column>,
+    n_schema_cols: usize,
+) {
+    assert_eq!(
+        cols.len(),
+        n_schema_cols,
+        "sorer chunk returned a different number of columns than the schema"
+    );
+    if data.is_empty() {
+        *data = cols;
+        return;
+    }
+    for (acc, col) in data.iter_mut().zip(cols.into_iter()) {
+        match (acc, col) {
+            (Column::Int(a), Column::Int(mut b)) => a.append(&mut b),
+            (Column::Bool(a), Column::Bool(mut b)) => a.append(&mut b),
+            (Column::Float(a), Column::Float(mut b)) => a.append(&mut b),
+            (Column::String(a), Column::String(mut b)) => a.append(&mut b),
+            _ => unreachable!("sorer chunk schema mismatch"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct SumRower {
+        sum: i64,
+    }
+
+    impl Rower for SumRower {
+        fn visit(&mut self, r: &Row) -> bool {
+            if let Data::Int(n) = r.get(0).unwrap() {
+                self.sum += n;
+            }
+            true
+        }
+
+        fn join(mut self, other: Self) -> Self {
+            self.sum += other.sum;
+            self
+        }
+    }
+
+    fn df_of_ints(values: &[i64]) -> DataFrame {
+        let schema = Schema::from("I");
+        let mut df = DataFrame::new(schema);
+        for &n in values {
+            let mut row = Row::new(&df.schema);
+            row.set_int(0, n).unwrap();
+            df.add_row(&row);
+        }
+        df
+    }
+
+    #[test]
+    fn test_pmap_single_threaded_fallback() {
+        let df = df_of_ints(&[1, 2, 3]);
+        assert!(df.n_rows() < df.n_threads);
+        let result = df.pmap(SumRower { sum: 0 });
+        assert_eq!(result.sum, 6);
+    }
+
+    #[test]
+    fn test_pmap_multi_threaded_split_and_join() {
+        let mut df = df_of_ints(&(1..=100).collect::<Vec<i64>>());
+        df.n_threads = 4;
+        let result = df.pmap(SumRower { sum: 0 });
+        assert_eq!(result.sum, (1..=100).sum());
+    }
+
+    #[test]
+    fn test_append_chunk_columns_first_chunk() {
+        let mut data = Vec::new();
+        append_chunk_columns(&mut data, vec![Column::Int(vec![Some(1), Some(2)])], 1);
+        assert_eq!(data, vec![Column::Int(vec![Some(1), Some(2)])]);
+    }
+
+    #[test]
+    fn test_append_chunk_columns_appends_across_chunks() {
+        let mut data = vec![Column::Int(vec![Some(1), Some(2)])];
+        append_chunk_columns(&mut data, vec![Column::Int(vec![Some(3)])], 1);
+        assert_eq!(data, vec![Column::Int(vec![Some(1), Some(2), Some(3)])]);
+    }
+
+    #[test]
+    fn test_append_chunk_columns_zero_length_chunk_is_a_noop() {
+        let mut data = vec![Column::Int(vec![Some(1)])];
+        append_chunk_columns(&mut data, vec![Column::Int(vec![])], 1);
+        assert_eq!(data, vec![Column::Int(vec![Some(1)])]);
+    }
+
+    #[test]
+    #[should_panic(expected = "different number of columns")]
+    fn test_append_chunk_columns_panics_on_column_count_mismatch() {
+        let mut data = Vec::new();
+        append_chunk_columns(&mut data, vec![Column::Int(vec![])], 2);
+    }
+}